@@ -2,6 +2,7 @@
 
 extern crate wasm_bindgen;
 
+use std::collections::VecDeque;
 use std::fmt;
 
 use wasm_bindgen::prelude::*;
@@ -11,8 +12,17 @@ extern {
     #[wasm_bindgen(js_namespace = console)]
     fn log(msg: &str);
 
+    #[wasm_bindgen(js_namespace = console, js_name = time)]
+    fn console_time(name: &str);
+
+    #[wasm_bindgen(js_namespace = console, js_name = timeEnd)]
+    fn console_time_end(name: &str);
+
     #[wasm_bindgen(js_namespace = Math)]
     fn random() -> f64;
+
+    #[wasm_bindgen(js_namespace = performance)]
+    fn now() -> f64;
 }
 
 // A macro to provide `println!(..)`-style syntax for `console.log` logging.
@@ -20,6 +30,33 @@ macro_rules! log {
     ($($t:tt)*) => (log(&format!($($t)*)))
 }
 
+// Number of ticks sampled for the smoothed `fps()` figure.
+const FPS_SAMPLE_SIZE: usize = 32;
+
+// Upper bound on a single grid dimension. Keeps `width * height` well
+// within `u32::MAX` (10_000 * 10_000 = 100_000_000) so it can't overflow,
+// and keeps the packed grid's memory use sane for a browser tab.
+const MAX_DIMENSION: u32 = 10_000;
+
+// RAII helper that brackets its lifetime with `console.time`/`console.timeEnd`,
+// so wrapping a block in a `Timer` shows it in the browser profiler.
+pub struct Timer<'a> {
+    name: &'a str,
+}
+
+impl<'a> Timer<'a> {
+    fn new(name: &'a str) -> Timer<'a> {
+        console_time(name);
+        Timer { name }
+    }
+}
+
+impl<'a> Drop for Timer<'a> {
+    fn drop(&mut self) {
+        console_time_end(self.name);
+    }
+}
+
 #[repr(u8)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Cell {
@@ -27,39 +64,123 @@ pub enum Cell {
     Alive = 1,
 }
 
+// Relative (row, column) offsets of the live cells in a few well-known
+// patterns, centered on (0, 0).
+const GLIDER: [(i32, i32); 5] = [
+    (-1, 0),
+    (0, 1),
+    (1, -1), (1, 0), (1, 1),
+];
+
+const PULSAR: [(i32, i32); 48] = [
+    (-6, -4), (-6, -3), (-6, -2), (-6, 2), (-6, 3), (-6, 4),
+    (-4, -6), (-4, -1), (-4, 1), (-4, 6),
+    (-3, -6), (-3, -1), (-3, 1), (-3, 6),
+    (-2, -6), (-2, -1), (-2, 1), (-2, 6),
+    (-1, -4), (-1, -3), (-1, -2), (-1, 2), (-1, 3), (-1, 4),
+    (1, -4), (1, -3), (1, -2), (1, 2), (1, 3), (1, 4),
+    (2, -6), (2, -1), (2, 1), (2, 6),
+    (3, -6), (3, -1), (3, 1), (3, 6),
+    (4, -6), (4, -1), (4, 1), (4, 6),
+    (6, -4), (6, -3), (6, -2), (6, 2), (6, 3), (6, 4),
+];
+
+const GOSPER_GLIDER_GUN: [(i32, i32); 36] = [
+    (-4, 7),
+    (-3, 5), (-3, 7),
+    (-2, -5), (-2, -4), (-2, 3), (-2, 4), (-2, 17), (-2, 18),
+    (-1, -6), (-1, -2), (-1, 3), (-1, 4), (-1, 17), (-1, 18),
+    (0, -17), (0, -16), (0, -7), (0, -1), (0, 3), (0, 4),
+    (1, -17), (1, -16), (1, -7), (1, -3), (1, -1), (1, 0), (1, 5), (1, 7),
+    (2, -7), (2, -1), (2, 7),
+    (3, -6), (3, -2),
+    (4, -5), (4, -4),
+];
+
 #[wasm_bindgen]
 pub struct Universe {
     width: u32,
     height: u32,
     cells: Vec<u8>,
+    cells_next: Vec<u8>,
+    wrap: bool,
+    profiling: bool,
+    frame_times: VecDeque<f64>,
 }
 
 /// Public methods, exported to JavaScript.
 #[wasm_bindgen]
 impl Universe {
     pub fn new() -> Universe {
-        let width = 64;
-        let height = 64;
-
-        let cells: Vec<_> = (0..width * height)
-            .map(|i| {
-                if random() < 0.5 {
-                    Cell::Alive
-                } else {
-                    Cell::Dead
-                }
-            })
-            .collect();
+        Universe::new_with_size(64, 64)
+    }
 
-        Universe::from_cells(width, height, &cells)
+    /// Create a universe of the given size, randomized at 50% live-cell
+    /// density. Width and height are clamped to `1..=MAX_DIMENSION`: a zero
+    /// dimension has no valid neighbor wrapping, and an unbounded dimension
+    /// can overflow `width * height` or exhaust memory.
+    pub fn new_with_size(width: u32, height: u32) -> Universe {
+        let width = width.clamp(1, MAX_DIMENSION);
+        let height = height.clamp(1, MAX_DIMENSION);
+        let mut universe = Universe::from_cells(width, height, &vec![Cell::Dead; (width * height) as usize]);
+        universe.reseed(0.5);
+        universe
     }
 
     fn from_cells(width: u32, height: u32, cells: &[Cell]) -> Universe {
         assert_eq!((width * height) as usize, cells.len());
-        let cells = cells.chunks(8).map(|chunk| {
+        let cells: Vec<u8> = cells.chunks(8).map(|chunk| {
             chunk.iter().rev().fold(0, |byte, &cell| (byte << 1) | (cell as u8))
         }).collect();
-        Universe { width, height, cells, }
+        let cells_next = vec![0; cells.len()];
+        Universe {
+            width,
+            height,
+            cells,
+            cells_next,
+            wrap: true,
+            profiling: false,
+            frame_times: VecDeque::with_capacity(FPS_SAMPLE_SIZE),
+        }
+    }
+
+    /// Resize the grid to the given width, zeroing all cells. Clamped to
+    /// `1..=MAX_DIMENSION`: a zero dimension has no valid neighbor
+    /// wrapping, and an unbounded dimension can overflow `width * height`
+    /// or exhaust memory.
+    pub fn set_width(&mut self, width: u32) {
+        self.width = width.clamp(1, MAX_DIMENSION);
+        self.reset_cells();
+    }
+
+    /// Resize the grid to the given height, zeroing all cells. Clamped to
+    /// `1..=MAX_DIMENSION`: a zero dimension has no valid neighbor
+    /// wrapping, and an unbounded dimension can overflow `width * height`
+    /// or exhaust memory.
+    pub fn set_height(&mut self, height: u32) {
+        self.height = height.clamp(1, MAX_DIMENSION);
+        self.reset_cells();
+    }
+
+    // Re-allocates `cells`/`cells_next` for the current width/height, zeroed.
+    fn reset_cells(&mut self) {
+        let byte_len = ((self.width * self.height) as usize + 7) / 8;
+        self.cells = vec![0; byte_len];
+        self.cells_next = vec![0; byte_len];
+    }
+
+    /// Refill the grid at the given live-cell probability.
+    pub fn reseed(&mut self, density: f64) {
+        for index in 0..(self.width * self.height) as usize {
+            let cell = if random() < density { Cell::Alive } else { Cell::Dead };
+            self.set_cell(index, cell);
+        }
+    }
+
+    /// Select the boundary mode: when `wrap` is false, off-grid neighbors
+    /// count as dead instead of wrapping toroidally.
+    pub fn set_wrap(&mut self, wrap: bool) {
+        self.wrap = wrap;
     }
 
     fn to_cells(&self) -> Vec<Cell> {
@@ -94,16 +215,108 @@ impl Universe {
         }
     }
 
+    fn set_cell(&mut self, index: usize, cell: Cell) {
+        let byte = index / 8;
+        let bit = index % 8;
+        match cell {
+            Cell::Alive => self.cells[byte] |= 1 << bit,
+            Cell::Dead => self.cells[byte] &= !(1 << bit),
+        }
+    }
+
+    // Resolves a possibly out-of-bounds (row, column) to a cell index,
+    // wrapping toroidally in both dimensions.
+    fn wrap_index(&self, row: i32, column: i32) -> usize {
+        let row = row.rem_euclid(self.height as i32) as u32;
+        let column = column.rem_euclid(self.width as i32) as u32;
+        self.get_index(row, column)
+    }
+
+    fn in_bounds(&self, row: u32, column: u32) -> bool {
+        row < self.height && column < self.width
+    }
+
+    /// Set the given (row, column) pairs, flattened, to `Cell::Alive`.
+    /// Out-of-bounds pairs are ignored.
+    pub fn set_cells(&mut self, cells: &[u32]) {
+        for pair in cells.chunks(2) {
+            if let [row, column] = *pair {
+                if !self.in_bounds(row, column) {
+                    continue;
+                }
+                let index = self.get_index(row, column);
+                self.set_cell(index, Cell::Alive);
+            }
+        }
+    }
+
+    /// Flip the state of a single cell, for click-to-edit in the browser.
+    /// If (row, column) is out of bounds, logs a message to the console and
+    /// leaves the grid unchanged.
+    pub fn toggle_cell(&mut self, row: u32, column: u32) {
+        if !self.in_bounds(row, column) {
+            log!("toggle_cell: ({}, {}) is out of bounds", row, column);
+            return;
+        }
+        let index = self.get_index(row, column);
+        let next = match self.get_cell(index) {
+            Cell::Alive => Cell::Dead,
+            Cell::Dead => Cell::Alive,
+        };
+        self.set_cell(index, next);
+    }
+
+    /// Blank the entire grid.
+    pub fn clear(&mut self) {
+        for byte in self.cells.iter_mut() {
+            *byte = 0;
+        }
+    }
+
+    /// Stamp a well-known pattern ("glider", "pulsar", or
+    /// "gosper_glider_gun") centered at (row, column), wrapping toroidally.
+    pub fn insert_pattern(&mut self, row: u32, column: u32, name: &str) {
+        let offsets: &[(i32, i32)] = match name {
+            "glider" => &GLIDER,
+            "pulsar" => &PULSAR,
+            "gosper_glider_gun" => &GOSPER_GLIDER_GUN,
+            _ => {
+                log!("insert_pattern: unknown pattern \"{}\"", name);
+                return;
+            }
+        };
+
+        for &(delta_row, delta_col) in offsets {
+            let index = self.wrap_index(row as i32 + delta_row, column as i32 + delta_col);
+            self.set_cell(index, Cell::Alive);
+        }
+    }
+
     fn live_neighbor_count(&self, row: u32, column: u32) -> u8 {
         let mut count = 0;
-        for delta_row in [self.height - 1, 0, 1].iter().cloned() {
-            for delta_col in [self.width - 1, 0, 1].iter().cloned() {
+        for delta_row in [-1i32, 0, 1].iter().cloned() {
+            for delta_col in [-1i32, 0, 1].iter().cloned() {
                 if delta_row == 0 && delta_col == 0 {
                     continue;
                 }
 
-                let neighbor_row = (row + delta_row) % self.height;
-                let neighbor_col = (column + delta_col) % self.width;
+                let neighbor_row = row as i32 + delta_row;
+                let neighbor_col = column as i32 + delta_col;
+
+                let (neighbor_row, neighbor_col) = if self.wrap {
+                    (
+                        neighbor_row.rem_euclid(self.height as i32) as u32,
+                        neighbor_col.rem_euclid(self.width as i32) as u32,
+                    )
+                } else {
+                    if neighbor_row < 0 || neighbor_row >= self.height as i32
+                        || neighbor_col < 0 || neighbor_col >= self.width as i32
+                    {
+                        continue;
+                    }
+                    (neighbor_row as u32, neighbor_col as u32)
+                };
+
                 let idx = self.get_index(neighbor_row, neighbor_col);
                 count += self.get_cell(idx) as u8;
             }
@@ -112,9 +325,61 @@ impl Universe {
     }
 
     pub fn tick(&mut self) {
+        self.step(false);
+    }
+
+    /// Like `tick`, but returns the flat indices of every cell whose state
+    /// changed, so the renderer can repaint only those cells.
+    pub fn tick_with_diff(&mut self) -> Vec<u32> {
+        self.step(true)
+    }
+
+    /// Enable or disable profiling: brackets `step()` in a
+    /// `console.time`/`console.timeEnd` pair and starts sampling timestamps
+    /// for `fps()`.
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.profiling = enabled;
+    }
+
+    /// A smoothed frames-per-second figure, sampled over the last
+    /// `FPS_SAMPLE_SIZE` ticks. Only populated while profiling is enabled.
+    pub fn fps(&self) -> f64 {
+        if self.frame_times.len() < 2 {
+            return 0.0;
+        }
+        let elapsed = self.frame_times.back().unwrap() - self.frame_times.front().unwrap();
+        if elapsed <= 0.0 {
+            return 0.0;
+        }
+        (self.frame_times.len() - 1) as f64 / elapsed * 1000.0
+    }
+
+    // Advances the simulation by one generation into `cells_next`, then
+    // swaps it in, avoiding a fresh allocation on every tick. When
+    // `want_diff` is false (plain `tick()`), the changed-index bookkeeping
+    // is skipped entirely so `tick()` pays neither the allocation nor the
+    // per-bit bookkeeping that only `tick_with_diff()` needs.
+    fn step(&mut self, want_diff: bool) -> Vec<u32> {
+        // Both the console.time bracket and the fps() timestamps are
+        // opt-in: they're the only things in this function that reach out
+        // to the JS host, so leaving them gated keeps tick() free to run
+        // off the browser (e.g. under `cargo test`) when profiling is off.
+        let _timer = if self.profiling {
+            self.frame_times.push_back(now());
+            if self.frame_times.len() > FPS_SAMPLE_SIZE {
+                self.frame_times.pop_front();
+            }
+            Some(Timer::new("Universe::step"))
+        } else {
+            None
+        };
+
         let len = (self.width * self.height) as usize;
-        self.cells = (0..(len + 7) / 8).map(|i| {
-            (0..8).fold(0, |acc, j| {
+        let mut diff = Vec::new();
+
+        for i in 0..(len + 7) / 8 {
+            let old_byte = self.cells[i];
+            let new_byte = (0..8).fold(0, |acc, j| {
                 let index = i * 8 + j;
                 let (row, col) = self.get_row_column(index);
                 let n = self.live_neighbor_count(row, col);
@@ -123,8 +388,27 @@ impl Universe {
                     _ => Cell::Dead,
                 };
                 acc | ((cell as u8) << j)
-            })
-        }).collect();
+            });
+
+            self.cells_next[i] = new_byte;
+
+            if !want_diff {
+                continue;
+            }
+
+            let mut changed = old_byte ^ new_byte;
+            while changed != 0 {
+                let bit = changed.trailing_zeros() as usize;
+                let index = i * 8 + bit;
+                if index < len {
+                    diff.push(index as u32);
+                }
+                changed &= changed - 1;
+            }
+        }
+
+        std::mem::swap(&mut self.cells, &mut self.cells_next);
+        diff
     }
 
     pub fn width(&self) -> u32 {
@@ -261,6 +545,44 @@ fn tick_rules_3_and_4() {
     );
 }
 
+#[test]
+fn tick_with_diff_reports_changed_indices() {
+    let before = [
+        Dead, Dead,  Dead,  Dead,  Dead,
+        Dead, Dead,  Alive, Dead,  Dead,
+        Dead, Alive, Alive, Alive, Dead,
+        Dead, Dead,  Alive, Dead,  Dead,
+        Dead, Dead,  Dead,  Dead,  Dead,
+    ];
+    let after = [
+        Dead, Dead,  Dead,  Dead,  Dead,
+        Dead, Alive, Alive, Alive, Dead,
+        Dead, Alive, Dead,  Alive, Dead,
+        Dead, Alive, Alive, Alive, Dead,
+        Dead, Dead,  Dead,  Dead,  Dead,
+    ];
+
+    let mut universe = Universe::from_cells(5, 5, &before);
+    let mut diff = universe.tick_with_diff();
+    diff.sort();
+
+    let mut expected: Vec<u32> = before.iter().zip(after.iter())
+        .enumerate()
+        .filter(|(_, (b, a))| b != a)
+        .map(|(i, _)| i as u32)
+        .collect();
+    expected.sort();
+
+    assert_eq!(diff, expected);
+    assert_eq!(&universe.to_cells()[..], &after[..]);
+}
+
+#[test]
+fn fps_reports_zero_before_any_ticks() {
+    let universe = Universe::from_cells(4, 4, &[Dead; 16]);
+    assert_eq!(universe.fps(), 0.0);
+}
+
 #[test]
 fn tick_cells_on_edge() {
     assert_tick(
@@ -282,3 +604,43 @@ fn tick_cells_on_edge() {
         ],
     );
 }
+
+fn assert_tick_no_wrap(w: u32, h: u32, before: &[Cell], after: &[Cell]) {
+    assert_eq!(before.len(), after.len());
+    assert_eq!(w as usize * h as usize, before.len());
+
+    let mut universe = Universe::from_cells(
+        w,
+        h,
+        &before,
+    );
+    universe.set_wrap(false);
+    universe.tick();
+
+    assert_eq!(&universe.to_cells()[..], after);
+}
+
+#[test]
+fn tick_cells_on_edge_no_wrap() {
+    // Same starting board as `tick_cells_on_edge`, but with wrapping off the
+    // off-grid neighbors of column 0 no longer reach column 4, so the
+    // pattern simply dies out instead of reappearing on the far edge.
+    assert_tick_no_wrap(
+        5,
+        5,
+        &[
+            Dead,  Dead, Dead, Dead,  Dead,
+            Dead,  Dead, Dead, Dead,  Dead,
+            Alive, Dead, Dead, Alive, Alive,
+            Dead,  Dead, Dead, Dead,  Dead,
+            Dead,  Dead, Dead, Dead,  Dead,
+        ],
+        &[
+            Dead, Dead, Dead, Dead, Dead,
+            Dead, Dead, Dead, Dead, Dead,
+            Dead, Dead, Dead, Dead, Dead,
+            Dead, Dead, Dead, Dead, Dead,
+            Dead, Dead, Dead, Dead, Dead,
+        ],
+    );
+}